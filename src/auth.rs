@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use axum::body::{Body, BoxBody};
+use axum::http::{header, Request, Response, StatusCode};
+use axum::response::{IntoResponse, Json};
+use serde_derive::Deserialize;
+use tower::{Layer, Service};
+
+/// One configured API key and the optional per-tenant controls attached to
+/// it: which dictionaries it may use and a request-rate cap.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiKeyConfig {
+    pub token: String,
+    #[serde(default)]
+    pub allowed_dicts: Option<Vec<String>>,
+    #[serde(default)]
+    pub requests_per_minute: Option<u32>,
+}
+
+/// `[auth]` section of a `--config` TOML file.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AuthConfig {
+    #[serde(default)]
+    pub api_keys: Vec<ApiKeyConfig>,
+}
+
+struct RateWindow {
+    started_at: Instant,
+    count: u32,
+}
+
+/// Shared state behind the auth layer: every configured key plus a simple
+/// per-key fixed-window request counter.
+pub struct AuthState {
+    keys: HashMap<String, ApiKeyConfig>,
+    usage: Mutex<HashMap<String, RateWindow>>,
+}
+
+impl AuthState {
+    pub fn new(keys: Vec<ApiKeyConfig>) -> Self {
+        AuthState {
+            keys: keys.into_iter().map(|key| (key.token.clone(), key)).collect(),
+            usage: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn authorize(&self, token: &str) -> Option<ApiKeyConfig> {
+        self.keys.get(token).cloned()
+    }
+
+    /// Fixed one-minute window counter; returns `false` once a key's
+    /// `requests_per_minute` has been exceeded for the current window.
+    /// `pub(crate)` so `/tokenize/stream` can apply it per tokenized batch,
+    /// not just once at connection upgrade like the tower layer below does.
+    pub(crate) fn check_rate_limit(&self, token: &str, requests_per_minute: u32) -> bool {
+        let mut usage = self.usage.lock().unwrap();
+        let window = usage.entry(token.to_owned()).or_insert_with(|| RateWindow {
+            started_at: Instant::now(),
+            count: 0,
+        });
+
+        if window.started_at.elapsed() >= Duration::from_secs(60) {
+            window.started_at = Instant::now();
+            window.count = 0;
+        }
+
+        window.count += 1;
+        window.count <= requests_per_minute
+    }
+}
+
+fn unauthorized(message: &str) -> Response<BoxBody> {
+    let body = serde_json::json!({ "kind": "unauthorized", "error": message });
+    (StatusCode::UNAUTHORIZED, Json(body)).into_response()
+}
+
+fn too_many_requests(message: &str) -> Response<BoxBody> {
+    let body = serde_json::json!({ "kind": "rate_limited", "error": message });
+    (StatusCode::TOO_MANY_REQUESTS, Json(body)).into_response()
+}
+
+/// Tower layer validating `Authorization: Bearer <token>` against the
+/// configured API keys before a request reaches `/tokenize`, so the handler
+/// itself stays unauthenticated-logic-free. The matched key is attached to
+/// the request as an extension for handlers that need its `allowed_dicts`.
+#[derive(Clone)]
+pub struct RequireApiKeyLayer {
+    state: Arc<AuthState>,
+}
+
+impl RequireApiKeyLayer {
+    pub fn new(state: Arc<AuthState>) -> Self {
+        RequireApiKeyLayer { state }
+    }
+}
+
+impl<S> Layer<S> for RequireApiKeyLayer {
+    type Service = RequireApiKey<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequireApiKey {
+            inner,
+            state: self.state.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RequireApiKey<S> {
+    inner: S,
+    state: Arc<AuthState>,
+}
+
+impl<S> Service<Request<Body>> for RequireApiKey<S>
+where
+    S: Service<Request<Body>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response<BoxBody>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
+        let state = self.state.clone();
+        let mut inner = self.inner.clone();
+
+        Box::pin(async move {
+            let token = req
+                .headers()
+                .get(header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "));
+
+            let key = match token.and_then(|token| state.authorize(token)) {
+                Some(key) => key,
+                None => return Ok(unauthorized("missing or invalid bearer token")),
+            };
+
+            if let Some(requests_per_minute) = key.requests_per_minute {
+                if !state.check_rate_limit(&key.token, requests_per_minute) {
+                    return Ok(too_many_requests("request rate limit exceeded for this key"));
+                }
+            }
+
+            req.extensions_mut().insert(key);
+            inner.call(req).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(token: &str, requests_per_minute: Option<u32>) -> ApiKeyConfig {
+        ApiKeyConfig {
+            token: token.to_owned(),
+            allowed_dicts: None,
+            requests_per_minute,
+        }
+    }
+
+    #[test]
+    fn allows_up_to_the_configured_limit() {
+        let state = AuthState::new(vec![key("t", Some(3))]);
+        assert!(state.check_rate_limit("t", 3));
+        assert!(state.check_rate_limit("t", 3));
+        assert!(state.check_rate_limit("t", 3));
+    }
+
+    #[test]
+    fn rejects_once_the_limit_is_exceeded() {
+        let state = AuthState::new(vec![key("t", Some(2))]);
+        assert!(state.check_rate_limit("t", 2));
+        assert!(state.check_rate_limit("t", 2));
+        assert!(!state.check_rate_limit("t", 2));
+    }
+
+    #[test]
+    fn resets_after_the_window_elapses() {
+        let state = AuthState::new(vec![key("t", Some(1))]);
+        {
+            let mut usage = state.usage.lock().unwrap();
+            let window = usage.get_mut("t");
+            assert!(window.is_none());
+        }
+
+        assert!(state.check_rate_limit("t", 1));
+        assert!(!state.check_rate_limit("t", 1));
+
+        // simulate the window having elapsed by backdating started_at,
+        // rather than sleeping 60s in a unit test
+        {
+            let mut usage = state.usage.lock().unwrap();
+            usage.get_mut("t").unwrap().started_at =
+                Instant::now() - Duration::from_secs(61);
+        }
+
+        assert!(state.check_rate_limit("t", 1));
+    }
+
+    #[test]
+    fn authorize_looks_up_by_token() {
+        let state = AuthState::new(vec![key("known", None)]);
+        assert!(state.authorize("known").is_some());
+        assert!(state.authorize("unknown").is_none());
+    }
+}
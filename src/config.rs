@@ -0,0 +1,40 @@
+use std::fs;
+use std::path::Path;
+
+use serde_derive::Deserialize;
+
+use lindera::error::LinderaErrorKind;
+use lindera::LinderaResult;
+
+use crate::auth::AuthConfig;
+use crate::filter::{CharFilterConfig, FilterPipeline, TokenFilterConfig};
+
+/// Server settings loaded from `--config`. Any field left out of the TOML
+/// keeps the value passed on the command line; only what's present here
+/// overrides the flat CLI flags.
+#[derive(Debug, Default, Deserialize)]
+pub struct ServerConfig {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub dict_type: Option<String>,
+    pub mode: Option<String>,
+    #[serde(default)]
+    pub char_filters: Vec<CharFilterConfig>,
+    #[serde(default)]
+    pub token_filters: Vec<TokenFilterConfig>,
+    #[serde(default)]
+    pub auth: AuthConfig,
+}
+
+impl ServerConfig {
+    pub fn from_path(path: &Path) -> LinderaResult<ServerConfig> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| LinderaErrorKind::Args.with_error(anyhow::anyhow!(err)))?;
+        toml::from_str(&content)
+            .map_err(|err| LinderaErrorKind::Args.with_error(anyhow::anyhow!(err)))
+    }
+
+    pub fn build_pipeline(&self) -> LinderaResult<FilterPipeline> {
+        FilterPipeline::from_config(&self.char_filters, &self.token_filters)
+    }
+}
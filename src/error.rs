@@ -0,0 +1,134 @@
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Json, Response};
+use serde_derive::Serialize;
+
+use lindera::error::LinderaErrorKind;
+use lindera::LinderaError;
+
+/// Stable, machine-readable error kind returned alongside every error body,
+/// so clients can branch on `kind` instead of parsing `error`.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[cfg_attr(test, derive(PartialEq, Eq))]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    InvalidInput,
+    Args,
+    PayloadTooLarge,
+    Internal,
+}
+
+/// Error returned by request handlers. Implements `IntoResponse` so it can
+/// be used as the error variant of a handler's `Result`, carrying the HTTP
+/// status code the failure maps to alongside a stable `kind` string.
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    kind: ErrorKind,
+    message: String,
+}
+
+impl ApiError {
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        ApiError {
+            status: StatusCode::BAD_REQUEST,
+            kind: ErrorKind::InvalidInput,
+            message: message.into(),
+        }
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        ApiError {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            kind: ErrorKind::Internal,
+            message: message.into(),
+        }
+    }
+
+    pub fn payload_too_large(message: impl Into<String>) -> Self {
+        ApiError {
+            status: StatusCode::PAYLOAD_TOO_LARGE,
+            kind: ErrorKind::PayloadTooLarge,
+            message: message.into(),
+        }
+    }
+
+    #[cfg(test)]
+    fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    #[cfg(test)]
+    fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = serde_json::json!({
+            "kind": self.kind,
+            "error": self.message,
+        });
+        (self.status, Json(body)).into_response()
+    }
+}
+
+impl From<LinderaError> for ApiError {
+    /// `LinderaErrorKind::Args` means the caller passed a bad dictionary or
+    /// mode name, so it is reported as a 400 with kind `"args"`; every other
+    /// kind comes from the dictionary/tokenizer itself and is treated as a
+    /// 500 with kind `"internal"`.
+    fn from(err: LinderaError) -> Self {
+        let message = err.to_string();
+        match err.kind() {
+            LinderaErrorKind::Args => ApiError {
+                status: StatusCode::BAD_REQUEST,
+                kind: ErrorKind::Args,
+                message,
+            },
+            _ => ApiError::internal(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_input_maps_to_400() {
+        let err = ApiError::invalid_input("bad request");
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn internal_maps_to_500() {
+        let err = ApiError::internal("boom");
+        assert_eq!(err.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(err.kind(), ErrorKind::Internal);
+    }
+
+    #[test]
+    fn lindera_args_error_maps_to_400_args() {
+        let lindera_err = LinderaErrorKind::Args.with_error(anyhow::anyhow!("bad dict_type"));
+        let err = ApiError::from(lindera_err);
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(err.kind(), ErrorKind::Args);
+    }
+
+    #[test]
+    fn payload_too_large_maps_to_413() {
+        let err = ApiError::payload_too_large("body too large");
+        assert_eq!(err.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(err.kind(), ErrorKind::PayloadTooLarge);
+    }
+
+    #[test]
+    fn other_lindera_error_maps_to_500_internal() {
+        let lindera_err = LinderaErrorKind::Parse.with_error(anyhow::anyhow!("tokenize failed"));
+        let err = ApiError::from(lindera_err);
+        assert_eq!(err.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(err.kind(), ErrorKind::Internal);
+    }
+}
@@ -0,0 +1,77 @@
+use axum::async_trait;
+use axum::body::{Bytes, HttpBody};
+use axum::extract::{FromRequest, RequestParts};
+use axum::BoxError;
+use serde::de::DeserializeOwned;
+
+use crate::error::ApiError;
+
+/// Body size `/tokenize` accepts, replacing axum's `ContentLengthLimit` so
+/// an oversize body is reported through `ApiError` rather than axum's
+/// default plain-text 413.
+pub const MAX_BODY_BYTES: u64 = 1024 * 5_000;
+
+/// Drop-in replacement for `axum::Json` that rejects malformed JSON, a
+/// wrong `Content-Type`, an oversize body, or missing required fields
+/// through `ApiError` instead of axum's default plain-text rejections — so
+/// every client mistake, not just the ones caught after extraction, returns
+/// the documented `{"kind": ..., "error": ...}` body.
+pub struct ApiJson<T>(pub T);
+
+#[async_trait]
+impl<T, B> FromRequest<B> for ApiJson<T>
+where
+    T: DeserializeOwned,
+    B: HttpBody + Send,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let content_type = req
+            .headers()
+            .get(axum::http::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        if !content_type.starts_with("application/json") {
+            return Err(ApiError::invalid_input(format!(
+                "expected `Content-Type: application/json`, got {:?}",
+                content_type
+            )));
+        }
+
+        if let Some(content_length) = req
+            .headers()
+            .get(axum::http::header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+        {
+            if content_length > MAX_BODY_BYTES {
+                return Err(ApiError::payload_too_large(format!(
+                    "body of {} bytes exceeds the {} byte limit",
+                    content_length, MAX_BODY_BYTES
+                )));
+            }
+        }
+
+        let bytes = Bytes::from_request(req)
+            .await
+            .map_err(|err| ApiError::invalid_input(err.to_string()))?;
+
+        // a chunked request has no Content-Length to check upfront, so the
+        // limit is also enforced against what was actually read
+        if bytes.len() as u64 > MAX_BODY_BYTES {
+            return Err(ApiError::payload_too_large(format!(
+                "body of {} bytes exceeds the {} byte limit",
+                bytes.len(),
+                MAX_BODY_BYTES
+            )));
+        }
+
+        let value = serde_json::from_slice(&bytes)
+            .map_err(|err| ApiError::invalid_input(format!("invalid JSON body: {}", err)))?;
+
+        Ok(ApiJson(value))
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::{HashMap, HashSet};
+
+use serde_derive::Deserialize;
+use unicode_normalization::UnicodeNormalization;
+
+use lindera::error::LinderaErrorKind;
+use lindera::LinderaResult;
+
+use crate::DetailedResult;
+
+/// One character filter, applied to the raw input text before tokenization.
+/// Declared in the `[[char_filters]]` array of a `--config` TOML file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CharFilterConfig {
+    /// Applies the given Unicode normalization form. The compatibility forms
+    /// (`nfkc`, `nfkd`) also fold full/half-width and compatibility variants,
+    /// which matters for CJK text where width variants would otherwise miss
+    /// dictionary entries; `nfc` and `nfd` leave those variants as they are.
+    UnicodeNormalize { form: UnicodeNormalizeForm },
+    /// Replaces every match of `pattern` with `replacement`.
+    Regex { pattern: String, replacement: String },
+    /// Replaces exact substrings via a fixed lookup table.
+    Mapping { map: HashMap<String, String> },
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnicodeNormalizeForm {
+    Nfc,
+    Nfd,
+    Nfkc,
+    Nfkd,
+}
+
+/// One token filter, applied to the tokenized output. Declared in the
+/// `[[token_filters]]` array of a `--config` TOML file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TokenFilterConfig {
+    Lowercase,
+    StopWords { words: Vec<String> },
+    Length {
+        #[serde(default)]
+        min: Option<usize>,
+        #[serde(default)]
+        max: Option<usize>,
+    },
+}
+
+trait CharFilter: Send + Sync {
+    fn apply(&self, text: String) -> String;
+}
+
+trait TokenFilter: Send + Sync {
+    fn apply(&self, tokens: Vec<DetailedResult>) -> Vec<DetailedResult>;
+}
+
+struct UnicodeNormalizeFilter(UnicodeNormalizeForm);
+
+impl CharFilter for UnicodeNormalizeFilter {
+    fn apply(&self, text: String) -> String {
+        match self.0 {
+            UnicodeNormalizeForm::Nfc => text.nfc().collect(),
+            UnicodeNormalizeForm::Nfd => text.nfd().collect(),
+            UnicodeNormalizeForm::Nfkc => text.nfkc().collect(),
+            UnicodeNormalizeForm::Nfkd => text.nfkd().collect(),
+        }
+    }
+}
+
+struct RegexFilter {
+    regex: regex::Regex,
+    replacement: String,
+}
+
+impl CharFilter for RegexFilter {
+    fn apply(&self, text: String) -> String {
+        self.regex
+            .replace_all(&text, self.replacement.as_str())
+            .into_owned()
+    }
+}
+
+struct MappingFilter(HashMap<String, String>);
+
+impl CharFilter for MappingFilter {
+    fn apply(&self, mut text: String) -> String {
+        for (from, to) in &self.0 {
+            text = text.replace(from.as_str(), to.as_str());
+        }
+        text
+    }
+}
+
+struct LowercaseFilter;
+
+impl TokenFilter for LowercaseFilter {
+    fn apply(&self, tokens: Vec<DetailedResult>) -> Vec<DetailedResult> {
+        tokens
+            .into_iter()
+            .map(|mut token| {
+                token.text = token.text.to_lowercase();
+                token
+            })
+            .collect()
+    }
+}
+
+struct StopWordsFilter(HashSet<String>);
+
+impl TokenFilter for StopWordsFilter {
+    fn apply(&self, tokens: Vec<DetailedResult>) -> Vec<DetailedResult> {
+        tokens
+            .into_iter()
+            .filter(|token| !self.0.contains(&token.text))
+            .collect()
+    }
+}
+
+struct LengthFilter {
+    min: Option<usize>,
+    max: Option<usize>,
+}
+
+impl TokenFilter for LengthFilter {
+    fn apply(&self, tokens: Vec<DetailedResult>) -> Vec<DetailedResult> {
+        tokens
+            .into_iter()
+            .filter(|token| {
+                let len = token.text.chars().count();
+                self.min.map_or(true, |min| len >= min) && self.max.map_or(true, |max| len <= max)
+            })
+            .collect()
+    }
+}
+
+/// Ordered chain of char filters (applied to the raw text before
+/// tokenization) and token filters (applied to the tokenized output),
+/// built once from a `--config` TOML file and threaded into every request.
+#[derive(Default)]
+pub struct FilterPipeline {
+    char_filters: Vec<Box<dyn CharFilter>>,
+    token_filters: Vec<Box<dyn TokenFilter>>,
+}
+
+impl FilterPipeline {
+    pub fn from_config(
+        char_filters: &[CharFilterConfig],
+        token_filters: &[TokenFilterConfig],
+    ) -> LinderaResult<FilterPipeline> {
+        let char_filters = char_filters
+            .iter()
+            .map(|config| -> LinderaResult<Box<dyn CharFilter>> {
+                Ok(match config {
+                    CharFilterConfig::UnicodeNormalize { form } => {
+                        Box::new(UnicodeNormalizeFilter(*form))
+                    }
+                    CharFilterConfig::Regex { pattern, replacement } => {
+                        let regex = regex::Regex::new(pattern).map_err(|err| {
+                            LinderaErrorKind::Args.with_error(anyhow::anyhow!(err))
+                        })?;
+                        Box::new(RegexFilter {
+                            regex,
+                            replacement: replacement.clone(),
+                        })
+                    }
+                    CharFilterConfig::Mapping { map } => Box::new(MappingFilter(map.clone())),
+                })
+            })
+            .collect::<LinderaResult<Vec<_>>>()?;
+
+        let token_filters = token_filters
+            .iter()
+            .map(|config| -> Box<dyn TokenFilter> {
+                match config {
+                    TokenFilterConfig::Lowercase => Box::new(LowercaseFilter),
+                    TokenFilterConfig::StopWords { words } => {
+                        Box::new(StopWordsFilter(words.iter().cloned().collect()))
+                    }
+                    TokenFilterConfig::Length { min, max } => Box::new(LengthFilter {
+                        min: *min,
+                        max: *max,
+                    }),
+                }
+            })
+            .collect();
+
+        Ok(FilterPipeline {
+            char_filters,
+            token_filters,
+        })
+    }
+
+    pub fn apply_char_filters(&self, text: String) -> String {
+        self.char_filters
+            .iter()
+            .fold(text, |text, filter| filter.apply(text))
+    }
+
+    pub fn apply_token_filters(&self, tokens: Vec<DetailedResult>) -> Vec<DetailedResult> {
+        self.token_filters
+            .iter()
+            .fold(tokens, |tokens, filter| filter.apply(tokens))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(text: &str) -> DetailedResult {
+        DetailedResult {
+            text: text.to_owned(),
+            detail: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn nfkc_folds_compatibility_and_width_forms() {
+        let pipeline = FilterPipeline::from_config(
+            &[CharFilterConfig::UnicodeNormalize {
+                form: UnicodeNormalizeForm::Nfkc,
+            }],
+            &[],
+        )
+        .unwrap();
+
+        // full-width "ABC" folds to ASCII, and the compatibility ligature
+        // folds to its expanded form, under NFKC but not under NFC
+        assert_eq!(pipeline.apply_char_filters("ＡＢＣ".to_owned()), "ABC");
+        assert_eq!(pipeline.apply_char_filters("ﬁ".to_owned()), "fi");
+    }
+
+    #[test]
+    fn nfc_does_not_fold_compatibility_forms() {
+        let pipeline = FilterPipeline::from_config(
+            &[CharFilterConfig::UnicodeNormalize {
+                form: UnicodeNormalizeForm::Nfc,
+            }],
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(pipeline.apply_char_filters("ﬁ".to_owned()), "ﬁ");
+    }
+
+    #[test]
+    fn length_filter_counts_chars_not_bytes() {
+        let pipeline = FilterPipeline::from_config(
+            &[],
+            &[TokenFilterConfig::Length {
+                min: Some(2),
+                max: Some(2),
+            }],
+        )
+        .unwrap();
+
+        // "日本" is 2 chars but 6 bytes; it must pass a max of 2 chars
+        let tokens = vec![token("日本"), token("日本語"), token("a")];
+        let filtered = pipeline.apply_token_filters(tokens);
+
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].text, "日本");
+    }
+}
@@ -1,12 +1,22 @@
-use axum::body::Bytes;
+mod auth;
+mod config;
+mod error;
+mod extract;
+mod filter;
+mod metrics;
+mod stream;
+
+use std::collections::HashMap;
 use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Instant;
 
-use axum::extract::{ContentLengthLimit, Extension};
-use axum::response::Json;
-use axum::routing::post;
+use axum::extract::Extension;
+use axum::http::header;
+use axum::response::{IntoResponse, Json};
+use axum::routing::{get, post};
 use axum::Router;
 use clap::Parser;
 use serde_derive::{Deserialize, Serialize};
@@ -19,6 +29,14 @@ use lindera::tokenizer::{DictionaryKind, Tokenizer, TokenizerConfig};
 use lindera::tokenizer::{DEFAULT_DICTIONARY_KIND, SUPPORTED_DICTIONARY_KIND};
 use lindera::LinderaResult;
 
+use crate::auth::{ApiKeyConfig, AuthState, RequireApiKeyLayer};
+use crate::config::ServerConfig;
+use crate::error::ApiError;
+use crate::extract::ApiJson;
+use crate::filter::FilterPipeline;
+use crate::metrics::{Metrics, MetricsLayer};
+use crate::stream::tokenize_stream;
+
 #[derive(Parser, Debug)]
 #[clap(version, about, long_about = None)]
 struct Args {
@@ -65,6 +83,54 @@ struct Args {
         display_order = 5
     )]
     mode: String,
+
+    /// Path to a TOML config file. When given, its settings and filter
+    /// pipeline take precedence over the flat CLI flags above.
+    #[clap(short = 'c', long = "config", value_name = "CONFIG", display_order = 6)]
+    config: Option<PathBuf>,
+
+    /// Require `Authorization: Bearer <token>` to match one of these keys.
+    /// Repeatable. For per-key dictionary/rate limits, set `[[auth.api_keys]]`
+    /// in `--config` instead.
+    #[clap(long = "api-key", value_name = "TOKEN", display_order = 7)]
+    api_key: Vec<String>,
+}
+
+/// Snapshot shared via `Extension` and served by `/status`: what this
+/// instance is running, not what any single request asked for.
+struct AppStatus {
+    start_time: Instant,
+    dict_kinds: Vec<String>,
+    mode: String,
+    version: &'static str,
+}
+
+/// Maps a `--dict-type`/`dict_type` string onto the `DictionaryKind` compiled
+/// into this binary, mirroring which `cfg(feature = ...)` arms are active.
+fn dict_kind_from_str(dict_type: &str) -> LinderaResult<DictionaryKind> {
+    match dict_type {
+        #[cfg(feature = "ipadic")]
+        "ipadic" => Ok(DictionaryKind::IPADIC),
+        #[cfg(feature = "unidic")]
+        "unidic" => Ok(DictionaryKind::UniDic),
+        #[cfg(feature = "ko-dic")]
+        "ko-dic" => Ok(DictionaryKind::KoDic),
+        #[cfg(feature = "cc-cedict")]
+        "cc-cedict" => Ok(DictionaryKind::CcCedict),
+        _ => Err(LinderaErrorKind::Args.with_error(anyhow::anyhow!(format!(
+            "{:?} are available for --dict-type",
+            SUPPORTED_DICTIONARY_KIND
+        )))),
+    }
+}
+
+/// Builds a `Tokenizer` for a single compiled-in dictionary, sharing the
+/// tokenization `mode` across every dictionary the server loads.
+fn build_tokenizer(kind: DictionaryKind, mode: Mode) -> LinderaResult<Tokenizer> {
+    let mut config = TokenizerConfig::default();
+    config.dictionary.kind = kind;
+    config.mode = mode;
+    Tokenizer::with_config(config)
 }
 
 #[tokio::main]
@@ -74,57 +140,127 @@ async fn main() -> LinderaResult<()> {
 
     let args = Args::parse();
 
-    let mut config = TokenizerConfig::default();
+    // a `--config` file overrides the flat CLI flags it sets; anything it
+    // leaves out falls back to the command line
+    let file_config = args
+        .config
+        .as_deref()
+        .map(ServerConfig::from_path)
+        .transpose()?
+        .unwrap_or_default();
 
-    // dictionary type
-    match args.dict_type.as_str() {
-        #[cfg(feature = "ipadic")]
-        "ipadic" => {
-            config.dictionary.kind = DictionaryKind::IPADIC;
-        }
-        #[cfg(feature = "unidic")]
-        "unidic" => {
-            config.dictionary.kind = DictionaryKind::UniDic;
-        }
-        #[cfg(feature = "ko-dic")]
-        "ko-dic" => {
-            config.dictionary.kind = DictionaryKind::KoDic;
-        }
-        #[cfg(feature = "cc-cedict")]
-        "cc-cedict" => {
-            config.dictionary.kind = DictionaryKind::CcCedict;
-        }
-        _ => {
-            return Err(LinderaErrorKind::Args.with_error(anyhow::anyhow!(format!(
-                "{:?} are available for --dict-type",
-                SUPPORTED_DICTIONARY_KIND
-            ))));
-        }
-    }
+    let host = file_config.host.clone().unwrap_or_else(|| args.host.clone());
+    let port = file_config.port.unwrap_or(args.port);
+    let dict_type = file_config
+        .dict_type
+        .clone()
+        .unwrap_or_else(|| args.dict_type.clone());
+    let mode_name = file_config
+        .mode
+        .clone()
+        .unwrap_or_else(|| args.mode.clone());
+
+    // the dictionary given on the command line (or config file) becomes the
+    // default used when a request does not specify its own `dict_type`
+    let default_dict_type = dict_kind_from_str(dict_type.as_str())?;
 
     // mode
-    match args.mode.as_str() {
-        "normal" => config.mode = Mode::Normal,
-        "search" => config.mode = Mode::Decompose(Penalty::default()),
-        "decompose" => config.mode = Mode::Decompose(Penalty::default()),
+    let mode = match mode_name.as_str() {
+        "normal" => Mode::Normal,
+        "search" => Mode::Decompose(Penalty::default()),
+        "decompose" => Mode::Decompose(Penalty::default()),
         _ => {
             return Err(LinderaErrorKind::Args
-                .with_error(anyhow::anyhow!("unsupported mode: {}", args.mode)));
+                .with_error(anyhow::anyhow!("unsupported mode: {}", mode_name)));
         }
-    }
+    };
+
+    let pipeline = file_config.build_pipeline()?;
+
+    // CLI `--api-key` tokens get no per-key restrictions; keys declared in
+    // `[[auth.api_keys]]` in the config file can add those. Auth is only
+    // enabled once at least one key is configured from either source.
+    let mut api_keys: Vec<ApiKeyConfig> = args
+        .api_key
+        .iter()
+        .map(|token| ApiKeyConfig {
+            token: token.clone(),
+            allowed_dicts: None,
+            requests_per_minute: None,
+        })
+        .collect();
+    api_keys.extend(file_config.auth.api_keys.clone());
+    let auth_state = if api_keys.is_empty() {
+        None
+    } else {
+        Some(Arc::new(AuthState::new(api_keys)))
+    };
+
+    // build a tokenizer for every dictionary compiled into this binary, so
+    // one deployment can serve requests for all of them concurrently
+    let mut tokenizers: HashMap<DictionaryKind, Arc<Tokenizer>> = HashMap::new();
+    #[cfg(feature = "ipadic")]
+    tokenizers.insert(
+        DictionaryKind::IPADIC,
+        Arc::new(build_tokenizer(DictionaryKind::IPADIC, mode.clone())?),
+    );
+    #[cfg(feature = "unidic")]
+    tokenizers.insert(
+        DictionaryKind::UniDic,
+        Arc::new(build_tokenizer(DictionaryKind::UniDic, mode.clone())?),
+    );
+    #[cfg(feature = "ko-dic")]
+    tokenizers.insert(
+        DictionaryKind::KoDic,
+        Arc::new(build_tokenizer(DictionaryKind::KoDic, mode.clone())?),
+    );
+    #[cfg(feature = "cc-cedict")]
+    tokenizers.insert(
+        DictionaryKind::CcCedict,
+        Arc::new(build_tokenizer(DictionaryKind::CcCedict, mode.clone())?),
+    );
 
-    // create tokenizer
-    let tokenizer = Tokenizer::with_config(config)?;
+    let status = AppStatus {
+        start_time: Instant::now(),
+        dict_kinds: tokenizers.keys().map(|kind| format!("{:?}", kind)).collect(),
+        mode: mode_name,
+        version: clap::crate_version!(),
+    };
 
-    let host = args.host;
-    let port = args.port;
     let ip = IpAddr::from_str(host.as_str()).unwrap();
     let addr = SocketAddr::new(ip, port);
 
+    let metrics = Arc::new(Metrics::new());
+
+    // the tokenize routes are the only ones that require a key; /healthz,
+    // /status and /metrics stay reachable for operators without one. The
+    // metrics layer wraps both so an extractor rejection (bad JSON, oversize
+    // body) or a stream upgrade is counted the same as a handler failure,
+    // not just what `do_tokenize` itself observes.
+    let mut tokenize_routes = Router::new()
+        .route("/tokenize", post(tokenize))
+        .route("/tokenize/stream", get(tokenize_stream));
+    if let Some(auth_state) = auth_state {
+        // also shared as a plain Extension (rather than only driving
+        // RequireApiKeyLayer) so `/tokenize/stream` can re-check a key's
+        // requests_per_minute per tokenized batch, not just once at upgrade
+        tokenize_routes = tokenize_routes
+            .layer(Extension(auth_state.clone()))
+            .layer(RequireApiKeyLayer::new(auth_state));
+    }
+    let tokenize_routes = tokenize_routes.layer(MetricsLayer::new(metrics.clone()));
+
     // build our application with a route
     let app = Router::new()
-        .route("/tokenize", post(tokenize))
-        .layer(Extension(Arc::new(tokenizer)));
+        .merge(tokenize_routes)
+        .route("/healthz", get(healthz))
+        .route("/status", get(status_handler))
+        .route("/metrics", get(metrics_handler))
+        .layer(Extension(Arc::new(tokenizers)))
+        .layer(Extension(default_dict_type))
+        .layer(Extension(metrics))
+        .layer(Extension(Arc::new(status)))
+        .layer(Extension(Arc::new(pipeline)));
 
     // start the HTTP server
     axum::Server::bind(&addr)
@@ -136,43 +272,160 @@ async fn main() -> LinderaResult<()> {
 }
 
 #[derive(Serialize, Deserialize)]
-struct DetailedResult {
-    text: String,
-    detail: Vec<String>,
+pub(crate) struct DetailedResult {
+    pub(crate) text: String,
+    pub(crate) detail: Vec<String>,
 }
 
-// basic handler that responds with a static string
-async fn tokenize(
-    ContentLengthLimit(bytes): ContentLengthLimit<Bytes, { 1024 * 5_000 }>,
-    Extension(tokenizer): Extension<Arc<Tokenizer>>,
-) -> Json<Value> {
-    let text = match String::from_utf8(bytes.to_vec()) {
-        Ok(text) => text,
-        Err(err) => {
-            error!("{}", err);
-            let err_json = serde_json::json!({ "error": format!("{}", err) });
-            return Json(err_json);
-        }
-    };
-    info!("text: {}", text);
+/// Body accepted by `/tokenize`. `texts` holds one or more input strings so
+/// that callers batching many short documents can amortize the HTTP
+/// round-trip instead of issuing one request per string.
+///
+/// `dict_type` selects which of the server's loaded dictionaries handles the
+/// request, falling back to the one given on the command line. There is no
+/// per-request `mode`: every loaded dictionary shares the single tokenization
+/// mode set at startup (`--mode`/config `mode`), so it isn't part of the
+/// envelope a client can vary.
+#[derive(Deserialize)]
+struct TokenizeRequest {
+    texts: Vec<String>,
+    #[serde(default)]
+    dict_type: Option<String>,
+}
 
-    // tokenize
-    let tokens = match tokenizer.tokenize(text.as_str()) {
-        Ok(tokens) => tokens,
-        Err(err) => {
-            error!("{}", err);
-            let err_json = serde_json::json!({ "error": format!("{}", err) });
-            return Json(err_json);
-        }
+/// Checks an API key's `allowed_dicts` restriction, if any, against the
+/// dictionary a request resolved to. Shared by `/tokenize` and
+/// `/tokenize/stream` so a key's allow-list is enforced the same way on
+/// both paths.
+pub(crate) fn check_dict_allowed(
+    dict_type: DictionaryKind,
+    api_key: Option<&ApiKeyConfig>,
+) -> Result<(), String> {
+    let allowed_dicts = match api_key.and_then(|key| key.allowed_dicts.as_ref()) {
+        Some(allowed_dicts) => allowed_dicts,
+        None => return Ok(()),
     };
 
-    let detailed_results: Vec<DetailedResult> = tokens
+    let is_allowed = allowed_dicts
         .iter()
-        .map(|token| DetailedResult {
-            text: token.text.to_owned(),
-            detail: tokenizer.word_detail(token.word_id).unwrap(),
-        })
-        .collect();
+        .any(|name| dict_kind_from_str(name).map(|kind| kind == dict_type).unwrap_or(false));
+
+    if is_allowed {
+        Ok(())
+    } else {
+        Err(format!(
+            "dictionary {:?} is not permitted for this API key",
+            dict_type
+        ))
+    }
+}
+
+/// Runs the actual tokenization, returning the produced token count alongside
+/// the response body so the caller can feed it into the request metrics.
+fn do_tokenize(
+    request: &TokenizeRequest,
+    tokenizers: &HashMap<DictionaryKind, Arc<Tokenizer>>,
+    default_dict_type: DictionaryKind,
+    pipeline: &FilterPipeline,
+    api_key: Option<&ApiKeyConfig>,
+) -> Result<(Value, usize), ApiError> {
+    let dict_type = match &request.dict_type {
+        Some(dict_type) => dict_kind_from_str(dict_type)?,
+        None => default_dict_type,
+    };
+
+    check_dict_allowed(dict_type, api_key).map_err(ApiError::invalid_input)?;
+
+    let tokenizer = tokenizers.get(&dict_type).ok_or_else(|| {
+        ApiError::invalid_input(format!(
+            "dictionary {:?} is not loaded on this server",
+            dict_type
+        ))
+    })?;
+
+    let mut results: Vec<Vec<DetailedResult>> = Vec::with_capacity(request.texts.len());
+    let mut tokens_produced = 0;
+    for text in &request.texts {
+        info!("text: {}", text);
+
+        let filtered_text = pipeline.apply_char_filters(text.clone());
+
+        let tokens = tokenizer.tokenize(filtered_text.as_str()).map_err(|err| {
+            error!("{}", err);
+            ApiError::from(err)
+        })?;
+
+        let detailed_results: Vec<DetailedResult> = tokens
+            .iter()
+            .map(|token| {
+                tokenizer
+                    .word_detail(token.word_id)
+                    .map(|detail| DetailedResult {
+                        text: token.text.to_owned(),
+                        detail,
+                    })
+                    .map_err(|err| {
+                        error!("{}", err);
+                        ApiError::internal(err.to_string())
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let detailed_results = pipeline.apply_token_filters(detailed_results);
+
+        tokens_produced += detailed_results.len();
+        results.push(detailed_results);
+    }
+
+    Ok((
+        serde_json::value::to_value(&results).unwrap(),
+        tokens_produced,
+    ))
+}
+
+// `ApiJson` itself rejects an oversize body with a 413 before this handler
+// runs; everything past that point is classified by `ApiError`, which
+// carries both the HTTP status and a stable `kind` string
+async fn tokenize(
+    ApiJson(request): ApiJson<TokenizeRequest>,
+    Extension(tokenizers): Extension<Arc<HashMap<DictionaryKind, Arc<Tokenizer>>>>,
+    Extension(default_dict_type): Extension<DictionaryKind>,
+    Extension(metrics): Extension<Arc<Metrics>>,
+    Extension(pipeline): Extension<Arc<FilterPipeline>>,
+    api_key: Option<Extension<ApiKeyConfig>>,
+) -> Result<Json<Value>, ApiError> {
+    // requests_total/tokenize_errors_total/request_duration_seconds are
+    // recorded by `MetricsLayer` around this whole route, so an extractor
+    // rejection above (bad JSON, oversize body) is counted too; only the
+    // token count below needs the parsed response to compute
+    let api_key = api_key.as_ref().map(|Extension(key)| key);
+    let (body, tokens_produced) = do_tokenize(&request, &tokenizers, default_dict_type, &pipeline, api_key)?;
+
+    metrics.tokens_produced_total.inc_by(tokens_produced as u64);
+    Ok(Json(body))
+}
+
+/// Returns 200 once the server is ready to accept `/tokenize` requests.
+/// Every dictionary is loaded synchronously before the router is built, so
+/// reaching this handler already implies readiness.
+async fn healthz() -> &'static str {
+    "ok"
+}
+
+/// Snapshot of which dictionaries/mode are loaded, plus uptime and build
+/// version, for operators to check what a running instance is serving.
+async fn status_handler(Extension(status): Extension<Arc<AppStatus>>) -> Json<Value> {
+    Json(serde_json::json!({
+        "version": status.version,
+        "mode": status.mode,
+        "dictionaries": status.dict_kinds,
+        "uptime_seconds": status.start_time.elapsed().as_secs(),
+    }))
+}
 
-    Json(serde_json::value::to_value(&detailed_results).unwrap())
+/// Exposes the Prometheus counters/histograms in text exposition format.
+async fn metrics_handler(Extension(metrics): Extension<Arc<Metrics>>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics.gather(),
+    )
 }
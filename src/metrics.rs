@@ -0,0 +1,154 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use axum::body::{Body, BoxBody};
+use axum::http::{Request, Response};
+use prometheus::{Encoder, Histogram, HistogramOpts, IntCounter, Opts, Registry, TextEncoder};
+use tower::{Layer, Service};
+
+/// Prometheus counters/histograms shared across every request via
+/// `Extension`, so operators can scrape `/metrics` instead of parsing logs.
+pub struct Metrics {
+    registry: Registry,
+    pub requests_total: IntCounter,
+    pub tokenize_errors_total: IntCounter,
+    pub request_duration_seconds: Histogram,
+    pub tokens_produced_total: IntCounter,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+
+        let requests_total = IntCounter::with_opts(Opts::new(
+            "lindera_server_requests_total",
+            "Total number of /tokenize requests handled",
+        ))
+        .unwrap();
+        let tokenize_errors_total = IntCounter::with_opts(Opts::new(
+            "lindera_server_tokenize_errors_total",
+            "Total number of failed /tokenize requests and /tokenize/stream batches",
+        ))
+        .unwrap();
+        let request_duration_seconds = Histogram::with_opts(HistogramOpts::new(
+            "lindera_server_request_duration_seconds",
+            "Time spent handling a /tokenize request, in seconds",
+        ))
+        .unwrap();
+        let tokens_produced_total = IntCounter::with_opts(Opts::new(
+            "lindera_server_tokens_produced_total",
+            "Total number of tokens produced across all /tokenize requests",
+        ))
+        .unwrap();
+
+        registry
+            .register(Box::new(requests_total.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(tokenize_errors_total.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(request_duration_seconds.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(tokens_produced_total.clone()))
+            .unwrap();
+
+        Metrics {
+            registry,
+            requests_total,
+            tokenize_errors_total,
+            request_duration_seconds,
+            tokens_produced_total,
+        }
+    }
+
+    /// Renders every registered metric in the Prometheus text exposition
+    /// format, ready to hand back as the `/metrics` response body.
+    pub fn gather(&self) -> String {
+        let metric_families = self.registry.gather();
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&metric_families, &mut buffer)
+            .unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Tower layer recording `requests_total`/`tokenize_errors_total`/
+/// `request_duration_seconds` around every request reaching the tokenize
+/// routes, so a rejection from an extractor (bad JSON, oversize body) or
+/// the `/tokenize/stream` upgrade is counted the same as a handler-level
+/// failure, rather than only what `do_tokenize` itself sees.
+#[derive(Clone)]
+pub struct MetricsLayer {
+    metrics: Arc<Metrics>,
+}
+
+impl MetricsLayer {
+    pub fn new(metrics: Arc<Metrics>) -> Self {
+        MetricsLayer { metrics }
+    }
+}
+
+impl<S> Layer<S> for MetricsLayer {
+    type Service = RecordMetrics<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RecordMetrics {
+            inner,
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RecordMetrics<S> {
+    inner: S,
+    metrics: Arc<Metrics>,
+}
+
+impl<S> Service<Request<Body>> for RecordMetrics<S>
+where
+    S: Service<Request<Body>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response<BoxBody>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let metrics = self.metrics.clone();
+        let mut inner = self.inner.clone();
+        let start = Instant::now();
+
+        Box::pin(async move {
+            metrics.requests_total.inc();
+            let result = inner.call(req).await;
+
+            metrics
+                .request_duration_seconds
+                .observe(start.elapsed().as_secs_f64());
+            if let Ok(response) = &result {
+                if response.status().is_client_error() || response.status().is_server_error() {
+                    metrics.tokenize_errors_total.inc();
+                }
+            }
+
+            result
+        })
+    }
+}
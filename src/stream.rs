@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::Extension;
+use axum::response::IntoResponse;
+use tracing::error;
+
+use lindera::tokenizer::{DictionaryKind, Tokenizer};
+
+use crate::auth::{ApiKeyConfig, AuthState};
+use crate::check_dict_allowed;
+use crate::filter::FilterPipeline;
+use crate::metrics::Metrics;
+use crate::DetailedResult;
+
+/// Upper bound on the sentence/line accumulator below, matching
+/// `extract::MAX_BODY_BYTES`, the cap `ApiJson` puts on `/tokenize`. Without
+/// it a client that never sends a boundary character could grow `buffer`
+/// without limit, defeating the bounded-memory point of streaming in the
+/// first place.
+const MAX_BUFFER_BYTES: usize = 1024 * 5_000;
+
+/// Upgrades `/tokenize/stream` to a WebSocket so a client can push a large
+/// document in chunks instead of buffering it whole under the
+/// `extract::MAX_BODY_BYTES` cap that guards `/tokenize`.
+pub async fn tokenize_stream(
+    ws: WebSocketUpgrade,
+    Extension(tokenizers): Extension<Arc<HashMap<DictionaryKind, Arc<Tokenizer>>>>,
+    Extension(default_dict_type): Extension<DictionaryKind>,
+    Extension(pipeline): Extension<Arc<FilterPipeline>>,
+    Extension(metrics): Extension<Arc<Metrics>>,
+    api_key: Option<Extension<ApiKeyConfig>>,
+    auth_state: Option<Extension<Arc<AuthState>>>,
+) -> impl IntoResponse {
+    let api_key = api_key.map(|Extension(key)| key);
+    let auth_state = auth_state.map(|Extension(state)| state);
+    ws.on_upgrade(move |socket| {
+        handle_stream(
+            socket,
+            tokenizers,
+            default_dict_type,
+            pipeline,
+            metrics,
+            api_key,
+            auth_state,
+        )
+    })
+}
+
+async fn handle_stream(
+    mut socket: WebSocket,
+    tokenizers: Arc<HashMap<DictionaryKind, Arc<Tokenizer>>>,
+    default_dict_type: DictionaryKind,
+    pipeline: Arc<FilterPipeline>,
+    metrics: Arc<Metrics>,
+    api_key: Option<ApiKeyConfig>,
+    auth_state: Option<Arc<AuthState>>,
+) {
+    if let Err(message) = check_dict_allowed(default_dict_type, api_key.as_ref()) {
+        let _ = socket
+            .send(Message::Text(serde_json::json!({ "error": message }).to_string()))
+            .await;
+        return;
+    }
+
+    let tokenizer = match tokenizers.get(&default_dict_type) {
+        Some(tokenizer) => tokenizer.clone(),
+        None => {
+            let message = format!("dictionary {:?} is not loaded on this server", default_dict_type);
+            let _ = socket
+                .send(Message::Text(serde_json::json!({ "error": message }).to_string()))
+                .await;
+            return;
+        }
+    };
+
+    // text accumulated across frames until a sentence/line boundary shows up
+    let mut buffer = String::new();
+
+    while let Some(Ok(message)) = socket.recv().await {
+        let chunk = match message {
+            Message::Text(text) => text,
+            Message::Binary(bytes) => match String::from_utf8(bytes) {
+                Ok(text) => text,
+                Err(err) => {
+                    error!("{}", err);
+                    let err_json = serde_json::json!({ "error": err.to_string() });
+                    if socket.send(Message::Text(err_json.to_string())).await.is_err() {
+                        return;
+                    }
+                    continue;
+                }
+            },
+            Message::Close(_) => break,
+            _ => continue,
+        };
+
+        buffer.push_str(&chunk);
+
+        while let Some(boundary) = sentence_boundary(&buffer) {
+            let sentence: String = buffer.drain(..boundary).collect();
+            if let Err(message) = check_stream_rate_limit(auth_state.as_deref(), api_key.as_ref()) {
+                let _ = socket
+                    .send(Message::Text(serde_json::json!({ "error": message }).to_string()))
+                    .await;
+                return;
+            }
+            let (response, tokens_produced) = tokenize_batch(&tokenizer, &pipeline, &sentence);
+            record_batch_metrics(&metrics, tokens_produced);
+            if socket.send(Message::Text(response)).await.is_err() {
+                return;
+            }
+        }
+
+        // no boundary showed up but the accumulator is already as large as
+        // the batch limit on /tokenize would allow; flush it as-is rather
+        // than let it grow unbounded
+        if buffer.len() >= MAX_BUFFER_BYTES {
+            if let Err(message) = check_stream_rate_limit(auth_state.as_deref(), api_key.as_ref()) {
+                let _ = socket
+                    .send(Message::Text(serde_json::json!({ "error": message }).to_string()))
+                    .await;
+                return;
+            }
+            let overflowed = std::mem::take(&mut buffer);
+            let (response, tokens_produced) = tokenize_batch(&tokenizer, &pipeline, &overflowed);
+            record_batch_metrics(&metrics, tokens_produced);
+            if socket.send(Message::Text(response)).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    if !buffer.trim().is_empty() {
+        if check_stream_rate_limit(auth_state.as_deref(), api_key.as_ref()).is_ok() {
+            let (response, tokens_produced) = tokenize_batch(&tokenizer, &pipeline, &buffer);
+            record_batch_metrics(&metrics, tokens_produced);
+            if socket.send(Message::Text(response)).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    let _ = socket
+        .send(Message::Text(serde_json::json!({ "done": true }).to_string()))
+        .await;
+}
+
+/// Finds the end of the first complete sentence or line in `buffer`, so the
+/// accumulator can tokenize as soon as a boundary arrives instead of
+/// waiting for the whole document.
+fn sentence_boundary(buffer: &str) -> Option<usize> {
+    buffer
+        .char_indices()
+        .find(|(_, ch)| matches!(ch, '\n' | '。' | '.' | '!' | '?'))
+        .map(|(idx, ch)| idx + ch.len_utf8())
+}
+
+/// Re-applies a key's `requests_per_minute` cap per tokenized batch. The
+/// auth layer only checks it once, on the GET that opens the WebSocket, so
+/// without this a key would get one rate-limited request and then
+/// unlimited tokenization for the lifetime of the connection.
+fn check_stream_rate_limit(
+    auth_state: Option<&AuthState>,
+    api_key: Option<&ApiKeyConfig>,
+) -> Result<(), String> {
+    let (api_key, auth_state) = match (api_key, auth_state) {
+        (Some(api_key), Some(auth_state)) => (api_key, auth_state),
+        _ => return Ok(()),
+    };
+    let requests_per_minute = match api_key.requests_per_minute {
+        Some(requests_per_minute) => requests_per_minute,
+        None => return Ok(()),
+    };
+
+    if auth_state.check_rate_limit(&api_key.token, requests_per_minute) {
+        Ok(())
+    } else {
+        Err("request rate limit exceeded for this key".to_owned())
+    }
+}
+
+/// Records a batch's outcome the same way `/tokenize` records a request:
+/// tokens produced on success, or a tokenize error, so the stream path
+/// shows up in `/metrics` instead of being invisible to it.
+fn record_batch_metrics(metrics: &Metrics, tokens_produced: Option<usize>) {
+    match tokens_produced {
+        Some(tokens_produced) => metrics.tokens_produced_total.inc_by(tokens_produced as u64),
+        None => metrics.tokenize_errors_total.inc(),
+    }
+}
+
+/// Tokenizes one accumulated batch and renders it as the JSON message sent
+/// back over the socket, alongside the token count for `/metrics` (`None`
+/// on failure).
+fn tokenize_batch(
+    tokenizer: &Tokenizer,
+    pipeline: &FilterPipeline,
+    text: &str,
+) -> (String, Option<usize>) {
+    let filtered_text = pipeline.apply_char_filters(text.to_owned());
+
+    let tokens = match tokenizer.tokenize(filtered_text.as_str()) {
+        Ok(tokens) => tokens,
+        Err(err) => {
+            error!("{}", err);
+            return (serde_json::json!({ "error": err.to_string() }).to_string(), None);
+        }
+    };
+
+    let detailed_results: Vec<DetailedResult> = match tokens
+        .iter()
+        .map(|token| {
+            tokenizer
+                .word_detail(token.word_id)
+                .map(|detail| DetailedResult {
+                    text: token.text.to_owned(),
+                    detail,
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(detailed_results) => detailed_results,
+        Err(err) => {
+            error!("{}", err);
+            return (serde_json::json!({ "error": err.to_string() }).to_string(), None);
+        }
+    };
+    let detailed_results = pipeline.apply_token_filters(detailed_results);
+    let tokens_produced = detailed_results.len();
+
+    (
+        serde_json::json!({ "results": detailed_results }).to_string(),
+        Some(tokens_produced),
+    )
+}